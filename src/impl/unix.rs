@@ -6,8 +6,13 @@ use std::os::fd::OwnedFd;
 use std::os::fd::{AsRawFd, FromRawFd as _};
 #[cfg(all(target_os = "linux", feature = "pidfd"))]
 use std::ptr::null;
+use std::process::{Termination, exit};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+#[cfg(all(target_os = "linux", feature = "pidfd", feature = "tokio"))]
+use std::os::fd::AsFd as _;
 
-use super::{Child, Fork};
+use super::{Child, ExitStatus, Fork, ForkBuilder};
 
 pub(super) type OwnedFileDescriptor = Option<OwnedFd>;
 
@@ -24,7 +29,7 @@ fn open_pidfd(_pid: u32) -> Result<OwnedFd> {
     Err(Error::other("Unsupported"))
 }
 
-pub(super) fn fork() -> Result<Fork> {
+fn fork_impl() -> Result<Fork> {
     match cvt(unsafe { libc::fork() })? {
         0 => Ok(Fork::Child),
         pid => {
@@ -40,7 +45,52 @@ pub(super) fn fork() -> Result<Fork> {
     }
 }
 
-fn wait_impl<const FLAGS: libc::c_int>(child: &Child) -> Result<Option<i32>> {
+pub(super) fn fork() -> Result<Fork> {
+    fork_impl()
+}
+
+pub(super) fn fork_with(builder: ForkBuilder) -> Result<Fork> {
+    match fork_impl()? {
+        Fork::Child => {
+            apply_identity(&builder);
+            if let Some(before_child) = builder.before_child {
+                before_child();
+            }
+            Ok(Fork::Child)
+        }
+        parent => Ok(parent),
+    }
+}
+
+// Order matters: dropping the group must happen before dropping the
+// user, since the process needs CAP_SETGID to call `setgroups`/`setgid`,
+// a privilege that `setuid` gives up.
+fn apply_identity(builder: &ForkBuilder) {
+    if let Some(groups) = &builder.groups {
+        if unsafe { libc::setgroups(groups.len() as libc::size_t, groups.as_ptr()) } == -1 {
+            abort_child();
+        }
+    }
+    if let Some(gid) = builder.gid {
+        if unsafe { libc::setgid(gid) } == -1 {
+            abort_child();
+        }
+    }
+    if let Some(uid) = builder.uid {
+        if unsafe { libc::setuid(uid) } == -1 {
+            abort_child();
+        }
+    }
+}
+
+/// Aborts the child after a failed identity-dropping syscall, rather
+/// than risk continuing with only some of the requested privileges
+/// dropped.
+fn abort_child() -> ! {
+    std::process::exit(127);
+}
+
+fn wait_impl<const FLAGS: libc::c_int>(child: &Child) -> Result<Option<ExitStatus>> {
     #[cfg(all(target_os = "linux", feature = "pidfd"))]
     if let Some(pidfd) = &child.descriptor {
         let mut info = unsafe { zeroed::<libc::siginfo_t>() };
@@ -57,7 +107,11 @@ fn wait_impl<const FLAGS: libc::c_int>(child: &Child) -> Result<Option<i32>> {
             return Ok(None);
         }
         let status = unsafe { info.si_status() };
-        return Ok(Some(status));
+        let exit_status = match info.si_code {
+            libc::CLD_KILLED | libc::CLD_DUMPED => ExitStatus::from_signal(status),
+            _ => ExitStatus::from_code(status),
+        };
+        return Ok(Some(exit_status));
     }
     let mut status = 0;
     let pid = cvt_r(|| unsafe { libc::waitpid(child.pid as _, &mut status as *mut _, FLAGS) })?;
@@ -65,48 +119,137 @@ fn wait_impl<const FLAGS: libc::c_int>(child: &Child) -> Result<Option<i32>> {
         return Ok(None);
     }
     if libc::WIFEXITED(status) {
-        Ok(Some(libc::WEXITSTATUS(status)))
+        Ok(Some(ExitStatus::from_code(libc::WEXITSTATUS(status))))
     } else if libc::WIFSIGNALED(status) {
-        Ok(Some(libc::WTERMSIG(status)))
+        Ok(Some(ExitStatus::from_signal(libc::WTERMSIG(status))))
     } else {
-        Ok(Some(-1))
+        Ok(Some(ExitStatus::from_code(-1)))
     }
 }
 
-pub(super) fn wait(child: &Child) -> Result<i32> {
-    wait_impl::<0>(child).map(|status| status.unwrap_or(-1))
+pub(super) fn wait(child: &Child) -> Result<ExitStatus> {
+    wait_impl::<0>(child).map(|status| status.unwrap_or(ExitStatus::from_code(-1)))
 }
 
-pub(super) fn try_wait(child: &Child) -> Result<Option<i32>> {
+pub(super) fn try_wait(child: &Child) -> Result<Option<ExitStatus>> {
     wait_impl::<{ libc::WNOHANG }>(child)
 }
 
-fn kill_impl(child: &Child) -> Result<()> {
+pub(super) fn wait_timeout(child: &Child, dur: Duration) -> Result<Option<ExitStatus>> {
+    #[cfg(all(target_os = "linux", feature = "pidfd"))]
+    if let Some(pidfd) = &child.descriptor {
+        let mut fds = [libc::pollfd {
+            fd: pidfd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+        let timeout_ms = i32::try_from(dur.as_millis()).unwrap_or(i32::MAX);
+        let ready = cvt_r(|| unsafe { libc::poll(fds.as_mut_ptr(), 1, timeout_ms) })?;
+        if ready == 0 {
+            // timed out, the child hasn't exited yet
+            return Ok(None);
+        }
+        return wait_impl::<{ libc::WNOHANG }>(child);
+    }
+
+    // No pidfd available, so fall back to polling waitpid(WNOHANG)
+    // with a short capped sleep between attempts until the deadline.
+    // `Instant::checked_add` can overflow for a `dur` near
+    // `Duration::MAX`; treat that as an unreachably far-off deadline
+    // rather than let it panic.
+    let deadline = Instant::now().checked_add(dur);
+    loop {
+        if let Some(status) = wait_impl::<{ libc::WNOHANG }>(child)? {
+            return Ok(Some(status));
+        }
+        let remaining = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Ok(None);
+                }
+                remaining
+            }
+            None => Duration::from_secs(u64::MAX),
+        };
+        sleep(remaining.min(Duration::from_millis(10)));
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "pidfd", feature = "tokio"))]
+pub(super) async fn wait_async(child: &Child) -> Result<ExitStatus> {
+    let pidfd = child
+        .descriptor
+        .as_ref()
+        .ok_or_else(|| Error::other("Unsupported"))?;
+    let async_fd = tokio::io::unix::AsyncFd::new(pidfd.as_fd())?;
+    loop {
+        let mut guard = async_fd.readable().await?;
+        if let Some(status) = wait_impl::<{ libc::WNOHANG }>(child)? {
+            return Ok(status);
+        }
+        guard.clear_ready();
+    }
+}
+
+#[cfg(all(feature = "tokio", not(all(target_os = "linux", feature = "pidfd"))))]
+pub(super) async fn wait_async(_child: &Child) -> Result<ExitStatus> {
+    Err(Error::other("Unsupported"))
+}
+
+fn send_signal(child: &Child, sig: libc::c_int) -> Result<()> {
     #[cfg(all(target_os = "linux", feature = "pidfd"))]
     if let Some(pidfd) = &child.descriptor {
         cvt(unsafe {
             libc::syscall(
                 libc::SYS_pidfd_send_signal,
                 pidfd.as_raw_fd(),
-                libc::SIGKILL,
+                sig,
                 null::<usize>(),
                 0,
             )
         })?;
         return Ok(());
     }
-    cvt(unsafe { libc::kill(child.pid as _, libc::SIGKILL) })?;
+    cvt(unsafe { libc::kill(child.pid as _, sig) })?;
     Ok(())
 }
 
-pub(super) fn kill(child: &Child) -> Result<()> {
-    match kill_impl(child) {
+pub(super) fn signal(child: &Child, sig: i32) -> Result<()> {
+    match send_signal(child, sig as libc::c_int) {
         Ok(()) => Ok(()),
         Err(err) if err.raw_os_error() == Some(libc::ESRCH) => Ok(()), // Process already exited
         Err(err) => Err(err),
     }
 }
 
+pub(super) fn kill(child: &Child) -> Result<()> {
+    signal(child, libc::SIGKILL)
+}
+
+pub(super) fn terminate(child: &Child) -> Result<()> {
+    signal(child, libc::SIGTERM)
+}
+
+pub(super) fn daemonize<T: Termination>(f: impl FnOnce() -> T) -> Result<()> {
+    let mut intermediate = super::fork_fn(move || {
+        // Leave the parent's session and controlling terminal so the
+        // grandchild below is fully detached. This can only fail if
+        // we are already a process group leader, which a freshly
+        // forked child never is, so the result is ignored.
+        unsafe { libc::setsid() };
+        match super::fork_fn(f) {
+            Ok(child) => child.detach(),
+            Err(_) => exit(1),
+        }
+    })?;
+    let status = intermediate.wait()?;
+    if !status.success() {
+        return Err(Error::other("failed to spawn the daemonized process"));
+    }
+    Ok(())
+}
+
 trait IsMinusOne {
     fn is_minus_one(&self) -> bool;
 }
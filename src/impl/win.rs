@@ -3,6 +3,8 @@ use core::ptr::{null, null_mut};
 use core::slice;
 use std::io::{Error, Result};
 use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::process::Termination;
+use std::time::Duration;
 
 use phnt::ffi::{
     NtCreateUserProcess, NtQueryInformationProcess, NtSetInformationObject, OBJ_INHERIT,
@@ -10,6 +12,8 @@ use phnt::ffi::{
     PROCESS_CREATE_FLAGS_INHERIT_HANDLES, PROCESS_HANDLE_SNAPSHOT_INFORMATION,
     PROCESS_HANDLE_TABLE_ENTRY_INFO, PROCESSINFOCLASS, PS_CREATE_INFO, ULONG,
 };
+#[cfg(feature = "tokio")]
+use windows::Win32::Foundation::{DUPLICATE_SAME_ACCESS, DuplicateHandle};
 use windows::Win32::Foundation::{
     CloseHandle, ERROR_ACCESS_DENIED, HANDLE, NTSTATUS, STATUS_BUFFER_OVERFLOW,
     STATUS_BUFFER_TOO_SMALL, STATUS_INFO_LENGTH_MISMATCH, STATUS_PROCESS_CLONED, STATUS_SUCCESS,
@@ -21,7 +25,7 @@ use windows::Win32::System::Threading::{
     THREAD_ALL_ACCESS, TerminateProcess, WaitForSingleObject,
 };
 
-use super::{Child, Fork};
+use super::{Child, ExitStatus, Fork, ForkBuilder};
 
 pub(super) type OwnedFileDescriptor = OwnedHandle;
 
@@ -86,7 +90,29 @@ pub(super) fn fork() -> Result<Fork> {
     }
 }
 
-pub(super) fn wait(child: &Child) -> Result<i32> {
+// There is no Windows equivalent of a Unix identity, so `uid`, `gid`,
+// and `groups` have no implementation here; reject them outright
+// rather than silently ignore a caller's request to drop privileges,
+// since only `before_child` can actually run.
+pub(super) fn fork_with(builder: ForkBuilder) -> Result<Fork> {
+    if builder.uid.is_some() || builder.gid.is_some() || builder.groups.is_some() {
+        return Err(Error::other(
+            "uid/gid/groups are not supported on this platform",
+        ));
+    }
+
+    match fork()? {
+        Fork::Child => {
+            if let Some(before_child) = builder.before_child {
+                before_child();
+            }
+            Ok(Fork::Child)
+        }
+        parent => Ok(parent),
+    }
+}
+
+pub(super) fn wait(child: &Child) -> Result<ExitStatus> {
     let event = unsafe { WaitForSingleObject(HANDLE(child.descriptor.as_raw_handle()), INFINITE) };
 
     if event != WAIT_OBJECT_0 {
@@ -101,10 +127,10 @@ pub(super) fn wait(child: &Child) -> Result<i32> {
         )
     }?;
 
-    Ok(code as _)
+    Ok(ExitStatus::from_code(code as _))
 }
 
-pub(super) fn try_wait(child: &Child) -> Result<Option<i32>> {
+pub(super) fn try_wait(child: &Child) -> Result<Option<ExitStatus>> {
     let event = unsafe { WaitForSingleObject(HANDLE(child.descriptor.as_raw_handle()), 0) };
 
     match event {
@@ -121,10 +147,92 @@ pub(super) fn try_wait(child: &Child) -> Result<Option<i32>> {
         )
     }?;
 
-    Ok(Some(code as _))
+    Ok(Some(ExitStatus::from_code(code as _)))
+}
+
+pub(super) fn wait_timeout(child: &Child, dur: Duration) -> Result<Option<ExitStatus>> {
+    // `u32::MAX` is `INFINITE`, so saturate one below it or a finite
+    // `dur` that merely overflows `u32` milliseconds would silently
+    // turn into an unbounded wait.
+    let timeout_ms = u32::try_from(dur.as_millis())
+        .unwrap_or(u32::MAX)
+        .min(u32::MAX - 1);
+    let event =
+        unsafe { WaitForSingleObject(HANDLE(child.descriptor.as_raw_handle()), timeout_ms) };
+
+    match event {
+        WAIT_OBJECT_0 => {}
+        WAIT_TIMEOUT => return Ok(None),
+        _ => return Err(Error::last_os_error()),
+    }
+
+    let mut code = 0u32;
+    unsafe {
+        GetExitCodeProcess(
+            HANDLE(child.descriptor.as_raw_handle()),
+            &mut code as *mut _,
+        )
+    }?;
+
+    Ok(Some(ExitStatus::from_code(code as _)))
+}
+
+#[cfg(feature = "tokio")]
+pub(super) async fn wait_async(child: &Child) -> Result<ExitStatus> {
+    // `spawn_blocking` requires a `'static` closure, so duplicate the
+    // process handle and hand the duplicate to the blocking task
+    // rather than trying to share `child.descriptor` across threads.
+    let process = HANDLE(child.descriptor.as_raw_handle());
+    let mut duplicate = HANDLE::default();
+    unsafe {
+        DuplicateHandle(
+            GetCurrentProcess(),
+            process,
+            GetCurrentProcess(),
+            &mut duplicate,
+            0,
+            false,
+            DUPLICATE_SAME_ACCESS,
+        )
+    }?;
+    let duplicate = unsafe { OwnedHandle::from_raw_handle(duplicate.0) };
+
+    tokio::task::spawn_blocking(move || {
+        let event =
+            unsafe { WaitForSingleObject(HANDLE(duplicate.as_raw_handle()), INFINITE) };
+
+        if event != WAIT_OBJECT_0 {
+            return Err(Error::last_os_error());
+        }
+
+        let mut code = 0u32;
+        unsafe {
+            GetExitCodeProcess(HANDLE(duplicate.as_raw_handle()), &mut code as *mut _)
+        }?;
+
+        Ok(ExitStatus::from_code(code as _))
+    })
+    .await
+    .map_err(Error::other)?
 }
 
 pub(super) fn kill(child: &Child) -> Result<()> {
+    terminate(child)
+}
+
+// The POSIX `SIGTERM` signal number, the only one Windows can emulate.
+const SIGTERM: i32 = 15;
+
+pub(super) fn signal(child: &Child, sig: i32) -> Result<()> {
+    match sig {
+        SIGTERM => terminate(child),
+        _ => Err(Error::other(
+            "this signal has no equivalent on this platform",
+        )),
+    }
+}
+
+pub(super) fn terminate(child: &Child) -> Result<()> {
     let result = unsafe { TerminateProcess(HANDLE(child.descriptor.as_raw_handle()), 1) };
     if let Err(err) = result {
         // TerminateProcess returns ERROR_ACCESS_DENIED if the process has already been
@@ -137,6 +245,14 @@ pub(super) fn kill(child: &Child) -> Result<()> {
     Ok(())
 }
 
+// `fork` already clones into a brand new, independent process on
+// Windows, so there is no session/terminal to detach from; just fork
+// `f` and detach the resulting handle so it outlives the caller.
+pub(super) fn daemonize<T: Termination>(f: impl FnOnce() -> T) -> Result<()> {
+    super::fork_fn(f)?.detach();
+    Ok(())
+}
+
 fn snapshot_all_handles() -> Result<Vec<PROCESS_HANDLE_TABLE_ENTRY_INFO>> {
     let mut buffer = vec![0u8; 0x800]; // 2kiB to start with
 
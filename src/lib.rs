@@ -5,6 +5,7 @@ mod r#impl;
 use std::io::Result;
 use std::panic::{AssertUnwindSafe, catch_unwind};
 use std::process::{ExitCode, Termination, exit};
+use std::time::Duration;
 
 /// Representation of a running or exited child process,
 /// similar to `std::process::Child`.
@@ -24,7 +25,53 @@ pub struct Child {
     pid: u32,
     #[allow(dead_code)]
     descriptor: r#impl::OwnedFileDescriptor,
-    status: Option<i32>,
+    status: Option<ExitStatus>,
+}
+
+/// Describes the result of a process after it has exited.
+///
+/// Unlike a bare exit code, this distinguishes a process that exited
+/// normally from one that was terminated by a signal, mirroring
+/// [`std::process::ExitStatus`]. On Windows, `signal` is always `None`,
+/// since `GetExitCodeProcess` only ever yields an exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitStatus {
+    code: Option<i32>,
+    signal: Option<i32>,
+}
+
+impl ExitStatus {
+    pub(crate) fn from_code(code: i32) -> Self {
+        ExitStatus {
+            code: Some(code),
+            signal: None,
+        }
+    }
+
+    #[cfg_attr(windows, allow(dead_code))]
+    pub(crate) fn from_signal(signal: i32) -> Self {
+        ExitStatus {
+            code: None,
+            signal: Some(signal),
+        }
+    }
+
+    /// Returns the exit code of the process, if it exited normally.
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+
+    /// Returns the number of the signal that terminated the process,
+    /// if it was terminated by one.
+    pub fn signal(&self) -> Option<i32> {
+        self.signal
+    }
+
+    /// Returns whether the process exited successfully, i.e. with
+    /// exit code `0`.
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
 }
 
 /// Result of a [`fork`] operation.
@@ -77,7 +124,7 @@ pub enum Fork {
 /// match fork()? {
 ///     Fork::Parent(mut child) => {
 ///         let status = child.wait()?;
-///         assert_eq!(status, 42);
+///         assert_eq!(status.code(), Some(42));
 ///     }
 ///     Fork::Child => {
 ///         std::process::exit(42);
@@ -102,7 +149,7 @@ pub fn fork() -> Result<Fork> {
 ///     println!("hello from child!");
 /// })?;
 /// let status = child.wait()?;
-/// assert_eq!(status, 0);
+/// assert_eq!(status.code(), Some(0));
 /// # std::io::Result::Ok(())
 /// ```
 pub fn fork_fn<T: Termination>(f: impl FnOnce() -> T) -> Result<Child> {
@@ -116,18 +163,152 @@ pub fn fork_fn<T: Termination>(f: impl FnOnce() -> T) -> Result<Child> {
     }
 }
 
+/// A builder for configuring what the child does immediately after
+/// [`fork`], before any user code runs in the new process.
+///
+/// This is modeled after the identity-related builders on
+/// [`std::os::unix::process::CommandExt`] (`uid`, `gid`, `groups`),
+/// plus a pre-exec-style hook that runs last. It lets a privileged
+/// server fork and immediately drop to an unprivileged account in the
+/// child.
+///
+/// On Windows there is no equivalent of a Unix identity, so setting
+/// `uid`, `gid`, or `groups` makes [`fork`](ForkBuilder::fork) return
+/// an error there instead of silently dropping the request;
+/// `before_child` still runs.
+///
+/// ## Example
+/// ```rust
+/// # use cutlery::*;
+/// let mut child = ForkBuilder::new()
+///     .uid(1000)
+///     .gid(1000)
+///     .fork_fn(|| {
+///         // runs with uid/gid 1000 in the child
+///     })?;
+/// child.wait()?;
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Default)]
+pub struct ForkBuilder {
+    uid: Option<u32>,
+    gid: Option<u32>,
+    groups: Option<Vec<u32>>,
+    before_child: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl ForkBuilder {
+    /// Creates a new builder with no identity changes configured.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the user id to switch to in the child.
+    ///
+    /// Applied after `gid` and `groups`, since dropping the group
+    /// must happen while the process still has the privileges to do so.
+    pub fn uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Sets the group id to switch to in the child, applied before `uid`.
+    pub fn gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Sets the supplementary groups to switch to in the child, applied
+    /// before `gid` and `uid`.
+    pub fn groups(mut self, groups: &[u32]) -> Self {
+        self.groups = Some(groups.to_vec());
+        self
+    }
+
+    /// Registers a closure to run in the child once the identity
+    /// changes above have succeeded, before user code runs.
+    pub fn before_child(mut self, f: impl FnOnce() + Send + 'static) -> Self {
+        self.before_child = Some(Box::new(f));
+        self
+    }
+
+    /// Forks the current process, applying the configured identity
+    /// changes and `before_child` hook in the child before it returns
+    /// to user code.
+    ///
+    /// If dropping any of the requested privileges fails, the child
+    /// aborts with a nonzero exit code rather than continue running
+    /// with only some of them dropped.
+    ///
+    /// All the same considerations from [`fork`] also apply here.
+    pub fn fork(self) -> Result<Fork> {
+        r#impl::fork_with(self)
+    }
+
+    /// Like [`fork`](ForkBuilder::fork), but runs `f` in the child and
+    /// exits once it returns, mirroring [`fork_fn`].
+    pub fn fork_fn<T: Termination>(self, f: impl FnOnce() -> T) -> Result<Child> {
+        let f = move || catch_unwind(AssertUnwindSafe(f));
+        match self.fork()? {
+            Fork::Parent(child) => Ok(child),
+            Fork::Child => match f().report() {
+                ExitCode::SUCCESS => exit(0),
+                _ => exit(1),
+            },
+        }
+    }
+}
+
+/// Runs `f` in a fully detached background process and exits, for
+/// fire-and-forget background workers that the caller never intends
+/// to wait on.
+///
+/// On Unix this is the classic double-fork idiom: fork, leave the
+/// parent's session and controlling terminal, fork again, and have
+/// the first child exit immediately so the grandchild is reparented
+/// to init. On Windows, [`fork`] already creates an independent
+/// process, so this forks `f` and [`detach`](Child::detach)es the
+/// resulting [`Child`] so it outlives the caller.
+///
+/// Since the resulting process is no longer a child of the calling
+/// process, there is nothing to wait on; that is the whole point of
+/// daemonizing.
+///
+/// ## Example
+/// ```rust,no_run
+/// # use cutlery::*;
+/// daemonize(|| {
+///     // runs fully detached from the caller
+/// })?;
+/// # std::io::Result::Ok(())
+/// ```
+pub fn daemonize<T: Termination>(f: impl FnOnce() -> T) -> Result<()> {
+    r#impl::daemonize(f)
+}
+
 impl Child {
     /// Returns the OS-assigned process identifier associated with this child.
     pub fn id(&self) -> u32 {
         self.pid
     }
 
+    /// Explicitly relinquishes this handle, stating the intent to not
+    /// wait on the child.
+    ///
+    /// This closes the pidfd/process handle this `Child` uses to
+    /// track the process; the process itself is unaffected and keeps
+    /// running. Since `Child` has no `Drop` impl, simply letting it go
+    /// out of scope already has this effect, but calling `detach`
+    /// explicitly tells a reader that the caller never intends to wait
+    /// on it, rather than having forgotten to.
+    pub fn detach(self) {}
+
     /// Waits for the child to exit completely,
     /// returning the status that it exited with.
     /// This function will continue to have the
     /// same return value after it has been called
     /// at least once.
-    pub fn wait(&mut self) -> Result<i32> {
+    pub fn wait(&mut self) -> Result<ExitStatus> {
         match self.status {
             Some(status) => Ok(status),
             None => {
@@ -153,7 +334,7 @@ impl Child {
     /// is returned. If the exit status is not available
     /// at this time then Ok(None) is returned. If an
     /// error occurs, then that error is returned.
-    pub fn try_wait(&mut self) -> Result<Option<i32>> {
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
         match self.status {
             Some(status) => Ok(Some(status)),
             None => {
@@ -163,6 +344,48 @@ impl Child {
         }
     }
 
+    /// Waits for the child to exit, but gives up and returns `Ok(None)`
+    /// if it has not exited within `dur`.
+    ///
+    /// This function will block the calling thread for at most `dur`.
+    /// If the child exits before the deadline elapses then
+    /// `Ok(Some(status))` is returned, and this function is guaranteed
+    /// to repeatedly return the same status on subsequent calls, same
+    /// as [`wait`](Child::wait).
+    pub fn wait_timeout(&mut self, dur: Duration) -> Result<Option<ExitStatus>> {
+        match self.status {
+            Some(status) => Ok(Some(status)),
+            None => {
+                self.status = r#impl::wait_timeout(self, dur)?;
+                Ok(self.status)
+            }
+        }
+    }
+
+    /// Waits for the child to exit completely without blocking the
+    /// calling thread, returning the status that it exited with.
+    ///
+    /// This lets a forked [`Child`] be awaited from inside a `tokio`
+    /// executor, e.g. to supervise many forked workers from a single
+    /// async task. Requires the `tokio` feature.
+    ///
+    /// On Linux this additionally requires the `pidfd` feature (and a
+    /// kernel with working `pidfd_open` support): with `tokio` alone,
+    /// there is no portable way to poll a child for readiness, so this
+    /// always returns an `Unsupported` error instead. Windows only
+    /// needs the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub async fn wait_async(&mut self) -> Result<ExitStatus> {
+        match self.status {
+            Some(status) => Ok(status),
+            None => {
+                let status = r#impl::wait_async(self).await?;
+                self.status = Some(status);
+                Ok(status)
+            }
+        }
+    }
+
     /// Forces the child process to exit. If the child has already exited, Ok(()) is returned.
     ///
     /// This is equivalent to sending a SIGKILL on Unix platforms.
@@ -177,6 +400,34 @@ impl Child {
             None => r#impl::kill(self),
         }
     }
+
+    /// Sends the given signal to the child process, e.g. to request a
+    /// graceful shutdown (`SIGTERM`), pause it (`SIGSTOP`), or resume it
+    /// (`SIGCONT`). If the child has already exited, Ok(()) is returned.
+    ///
+    /// On Windows only signals with a portable equivalent are
+    /// supported (currently just [`terminate`](Child::terminate)'s
+    /// `SIGTERM`); any other signal returns an error.
+    pub fn signal(&mut self, sig: i32) -> Result<()> {
+        match self.status {
+            Some(_) => Ok(()),
+            None => r#impl::signal(self, sig),
+        }
+    }
+
+    /// Requests that the child process terminate gracefully, by
+    /// sending it `SIGTERM` on Unix platforms. If the child has
+    /// already exited, Ok(()) is returned.
+    ///
+    /// On Windows there is no portable notion of a graceful
+    /// termination request, so this behaves the same as
+    /// [`kill`](Child::kill).
+    pub fn terminate(&mut self) -> Result<()> {
+        match self.status {
+            Some(_) => Ok(()),
+            None => r#impl::terminate(self),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,18 +1,21 @@
+#[cfg(feature = "tokio")]
+use std::io::ErrorKind;
 use std::io::{Read as _, Write as _, pipe, stdout};
+use std::mem::size_of;
 use std::process::exit;
 use std::thread::sleep;
 use std::time::Duration;
 
 use stdio_utils::StdioOverride;
 
-use crate::{Fork, fork, fork_fn};
+use crate::{Fork, ForkBuilder, daemonize, fork, fork_fn};
 
 #[test]
 fn test_fork_basic() {
     match fork().unwrap() {
         Fork::Parent(mut child) => {
-            let exit_code = child.wait().unwrap();
-            assert_eq!(exit_code, 42);
+            let status = child.wait().unwrap();
+            assert_eq!(status.code(), Some(42));
         }
         Fork::Child => {
             exit(42);
@@ -45,10 +48,10 @@ fn test_fork_try_wait() {
             sleep(Duration::from_secs(2));
 
             let status = child.try_wait().unwrap();
-            assert_eq!(status, Some(42));
+            assert_eq!(status.and_then(|status| status.code()), Some(42));
 
             let status = child.wait().unwrap();
-            assert_eq!(status, 42);
+            assert_eq!(status.code(), Some(42));
         }
         Fork::Child => {
             sleep(Duration::from_secs(1));
@@ -57,6 +60,190 @@ fn test_fork_try_wait() {
     }
 }
 
+#[test]
+fn test_fork_wait_timeout() {
+    match fork().unwrap() {
+        Fork::Parent(mut child) => {
+            let status = child.wait_timeout(Duration::from_millis(100)).unwrap();
+            assert_eq!(status, None);
+
+            let status = child.wait_timeout(Duration::from_secs(2)).unwrap();
+            assert_eq!(status.and_then(|status| status.code()), Some(42));
+
+            let status = child.wait().unwrap();
+            assert_eq!(status.code(), Some(42));
+        }
+        Fork::Child => {
+            sleep(Duration::from_secs(1));
+            exit(42);
+        }
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn test_fork_wait_async() {
+    match fork().unwrap() {
+        Fork::Parent(mut child) => {
+            match child.wait_async().await {
+                Ok(status) => assert_eq!(status.code(), Some(42)),
+                // `wait_async` needs a working pidfd on Linux; fall
+                // back to a blocking wait if this environment doesn't
+                // support it (e.g. an older kernel, or pidfd_open
+                // blocked by a sandbox), so the child still gets reaped.
+                Err(err) if err.kind() == ErrorKind::Other => {
+                    let status = child.wait().unwrap();
+                    assert_eq!(status.code(), Some(42));
+                }
+                Err(err) => panic!("wait_async failed: {err}"),
+            }
+        }
+        Fork::Child => {
+            sleep(Duration::from_millis(100));
+            exit(42);
+        }
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn test_fork_signal() {
+    match fork().unwrap() {
+        Fork::Parent(mut child) => {
+            child.signal(libc::SIGUSR1).unwrap();
+
+            let status = child.wait().unwrap();
+            assert_eq!(status.signal(), Some(libc::SIGUSR1));
+        }
+        Fork::Child => {
+            sleep(Duration::from_secs(5));
+            exit(0);
+        }
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn test_fork_killed_by_signal() {
+    match fork().unwrap() {
+        Fork::Parent(mut child) => {
+            child.kill().unwrap();
+
+            let status = child.wait().unwrap();
+            assert_eq!(status.code(), None);
+            assert_eq!(status.signal(), Some(libc::SIGKILL));
+            assert!(!status.success());
+        }
+        Fork::Child => {
+            sleep(Duration::from_secs(5));
+            exit(0);
+        }
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn test_fork_terminate() {
+    match fork().unwrap() {
+        Fork::Parent(mut child) => {
+            child.terminate().unwrap();
+
+            let status = child.wait().unwrap();
+            assert_eq!(status.signal(), Some(libc::SIGTERM));
+        }
+        Fork::Child => {
+            sleep(Duration::from_secs(5));
+            exit(0);
+        }
+    }
+}
+
+#[test]
+fn test_fork_builder_before_child() {
+    let (mut r, mut w) = pipe().unwrap();
+
+    let mut child = ForkBuilder::new()
+        .before_child(move || {
+            w.write_all(b"hello world").unwrap();
+        })
+        .fork_fn(|| {})
+        .unwrap();
+
+    let mut buf = [0; 11];
+    r.read_exact(&mut buf).unwrap();
+
+    assert_eq!(&buf, b"hello world");
+
+    child.wait().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_fork_builder_identity() {
+    // `nobody`/`nogroup` on a typical Linux system.
+    const UID: u32 = 65534;
+    const GID: u32 = 65534;
+
+    let (mut r, mut w) = pipe().unwrap();
+
+    let mut child = ForkBuilder::new()
+        .uid(UID)
+        .gid(GID)
+        .groups(&[GID])
+        .fork_fn(move || {
+            let uid = unsafe { libc::getuid() };
+            let gid = unsafe { libc::getgid() };
+            let mut groups = [0u32; 1];
+            let ngroups = unsafe { libc::getgroups(groups.len() as _, groups.as_mut_ptr()) };
+            w.write_all(&uid.to_ne_bytes()).unwrap();
+            w.write_all(&gid.to_ne_bytes()).unwrap();
+            w.write_all(&ngroups.to_ne_bytes()).unwrap();
+            w.write_all(&groups[0].to_ne_bytes()).unwrap();
+        })
+        .unwrap();
+
+    let mut buf = [0u8; size_of::<u32>() * 3 + size_of::<i32>()];
+    r.read_exact(&mut buf).unwrap();
+
+    let uid = u32::from_ne_bytes(buf[0..4].try_into().unwrap());
+    let gid = u32::from_ne_bytes(buf[4..8].try_into().unwrap());
+    let ngroups = i32::from_ne_bytes(buf[8..12].try_into().unwrap());
+    let group = u32::from_ne_bytes(buf[12..16].try_into().unwrap());
+
+    assert_eq!(uid, UID);
+    assert_eq!(gid, GID);
+    assert_eq!(ngroups, 1);
+    assert_eq!(group, GID);
+
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_fork_detach() {
+    let child = fork_fn(|| {
+        exit(42);
+    })
+    .unwrap();
+
+    // detach never blocks and consumes the handle
+    child.detach();
+}
+
+#[test]
+fn test_daemonize() {
+    let (mut r, mut w) = pipe().unwrap();
+
+    daemonize(move || {
+        w.write_all(b"hello world").unwrap();
+    })
+    .unwrap();
+
+    let mut buf = [0; 11];
+    r.read_exact(&mut buf).unwrap();
+
+    assert_eq!(&buf, b"hello world");
+}
+
 #[test]
 fn test_fork_pipe() {
     let (mut r, mut w) = pipe().unwrap();